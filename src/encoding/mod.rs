@@ -0,0 +1 @@
+pub mod variable_byte_integer;
@@ -0,0 +1,53 @@
+use crate::utils::buffer_reader::ParseError;
+
+/// Decodes the MQTTv5 variable byte integer encoding (1-4 Bytes, continuation bit in bit 7 of
+/// each Byte, 7 bits of value per Byte, least significant Byte first).
+pub struct VariableByteIntegerDecoder {}
+
+impl VariableByteIntegerDecoder {
+    pub fn decode(variable_byte_integer: [u8; 4]) -> Result<u32, ParseError> {
+        let mut value: u32 = 0;
+        let mut multiplier: u32 = 1;
+        for byte in variable_byte_integer.iter() {
+            value = value + (byte & 0x7F) as u32 * multiplier;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+            multiplier = multiplier * 128;
+        }
+        return Err(ParseError::VariableByteIntegerError);
+    }
+}
+
+/// Encodes a `u32` back into the MQTTv5 variable byte integer encoding. Counterpart to
+/// `VariableByteIntegerDecoder`.
+pub struct VariableByteIntegerEncoder {}
+
+impl VariableByteIntegerEncoder {
+    /// Largest value representable by a 4-Byte variable byte integer (`0x7F` repeated 4 times).
+    pub const MAX_VARIABLE_BYTE_INTEGER: u32 = 268_435_455;
+
+    /// Encodes `value`, returning a 4-Byte buffer and how many of its leading Bytes are
+    /// meaningful (the rest are left as `0` and should be ignored by the caller).
+    pub fn encode(value: u32) -> Result<([u8; 4], usize), ParseError> {
+        if value > Self::MAX_VARIABLE_BYTE_INTEGER {
+            return Err(ParseError::VariableByteIntegerError);
+        }
+        let mut encoded: [u8; 4] = [0; 4];
+        let mut remainder = value;
+        let mut len: usize = 0;
+        loop {
+            let mut encoded_byte = (remainder % 128) as u8;
+            remainder = remainder / 128;
+            if remainder > 0 {
+                encoded_byte = encoded_byte | 0x80;
+            }
+            encoded[len] = encoded_byte;
+            len = len + 1;
+            if remainder == 0 {
+                break;
+            }
+        }
+        return Ok((encoded, len));
+    }
+}
@@ -1,12 +1,28 @@
 use super::property::Property;
 use super::packet_type::PacketType;
+use crate::encoding::variable_byte_integer::VariableByteIntegerEncoder;
 use crate::utils::buffer_reader::BuffReader;
 use crate::utils::buffer_reader::EncodedString;
 use crate::utils::buffer_reader::BinaryData;
 use crate::utils::buffer_reader::ParseError;
 use crate::packet::mqtt_packet::Packet;
+use crate::packet::FixedHeaderPacket;
 use heapless::Vec;
 
+/// Bytes reserved in front of the encoded body for the fixed header (1 Byte) and the worst
+/// case 4-Byte remaining-length varint, so the body can be written once and the varint
+/// back-patched in front of it once its own size is known.
+const HEADER_RESERVE: usize = 5;
+
+/// Returns `ParseError::IndexOutOfBounce` instead of letting a later write slice-index panic
+/// when the caller handed `encode_connack_packet` a buffer too small to hold `needed` Bytes.
+fn require_capacity(buffer: &[u8], needed: usize) -> Result<(), ParseError> {
+    if buffer.len() < needed {
+        return Err(ParseError::IndexOutOfBounce);
+    }
+    return Ok(());
+}
+
 
 pub const MAX_PROPERTIES: usize = 18;
 
@@ -25,52 +41,213 @@ pub struct ConnackPacket<'a> {
 
 impl<'a> ConnackPacket<'a> {
 
-    pub fn decode_fixed_header(& mut self, buff_reader: & mut BuffReader) -> PacketType {
-        let first_byte: u8 = buff_reader.readU8().unwrap();
-        self.fixed_header = first_byte;
-        self.remain_len = buff_reader.readVariableByteInt().unwrap();
-        return PacketType::from(self.fixed_header);
-    }
-
-    pub fn decode_properties(& mut self, buff_reader: & mut BuffReader<'a>) {
-        self.property_len = buff_reader.readVariableByteInt().unwrap();
+    /// Decodes the CONNACK property list into `self.properties`, up to `MAX_PROPERTIES`. Rewinds
+    /// to the start of the list and returns `Ok(None)` if a property is only partially buffered.
+    pub fn decode_properties(& mut self, buff_reader: & mut BuffReader<'a>) -> Result<Option<()>, ParseError> {
+        let start = buff_reader.position;
+        // A retry after `Ok(None)` decodes the whole list again, so drop what an earlier try pushed.
+        self.properties.clear();
+        self.property_len = match buff_reader.read_variable_byte_int() {
+            Ok(Some(res)) => res,
+            Ok(None) => { buff_reader.position = start; return Ok(None); },
+            Err(err) => return Err(err),
+        };
         let mut x: u32 = 0;
-        let mut prop: Result<Property, ParseError>;
-        loop {
-            let mut res: Property;
-            prop = Property::decode(buff_reader);
-            if let Ok(res) = prop {
-                log::info!("Parsed property {:?}", res);
-                x = x + res.len() as u32 + 1;
-                self.properties.push(res);
-            } else {
-                // error handlo
-                log::error!("Problem during property decoding");
-            }
-            
-            if x == self.property_len {
-                break;
+        while x < self.property_len {
+            match Property::decode(buff_reader) {
+                Ok(Some(res)) => {
+                    log::info!("Parsed property {:?}", res);
+                    x = x + res.len() as u32 + 1;
+                    if x > self.property_len {
+                        log::error!("Decoded properties overran declared property_len");
+                        return Err(ParseError::DecodingError);
+                    }
+                    match self.properties.push(res) {
+                        Ok(()) => {},
+                        Err(_res) => {
+                            log::error!("Too many properties for MAX_PROPERTIES capacity");
+                            return Err(ParseError::PropertyListFull);
+                        },
+                    }
+                },
+                Ok(None) => {
+                    buff_reader.position = start;
+                    return Ok(None);
+                },
+                Err(err) => {
+                    log::error!("Problem during property decoding");
+                    return Err(err);
+                },
             }
         }
+        return Ok(Some(()));
     }
 
-    pub fn decode_connack_packet(& mut self, buff_reader: & mut BuffReader<'a>) {
-        
-        if self.decode_fixed_header(buff_reader) != (PacketType::Connack).into() {
+    /// Decodes the fixed header, ack flags, reason code and properties of a CONNACK packet in
+    /// order, rewinding to this call's starting position and returning `Ok(None)` the moment
+    /// any of them turns out to be short.
+    pub fn decode_connack_packet(& mut self, buff_reader: & mut BuffReader<'a>) -> Result<Option<()>, ParseError> {
+        let start = buff_reader.position;
+        let packet_type = match self.decode_fixed_header(buff_reader) {
+            Ok(Some(res)) => res,
+            Ok(None) => { buff_reader.position = start; return Ok(None); },
+            Err(err) => return Err(err),
+        };
+        if packet_type != (PacketType::Connack).into() {
             log::error!("Packet you are trying to decode is not CONNACK packet!");
-            return;
+            return Err(ParseError::DecodingError);
+        }
+        self.ack_flags = match buff_reader.read_u8() {
+            Ok(Some(res)) => res,
+            Ok(None) => { buff_reader.position = start; return Ok(None); },
+            Err(err) => return Err(err),
+        };
+        self.connect_reason_code = match buff_reader.read_u8() {
+            Ok(Some(res)) => res,
+            Ok(None) => { buff_reader.position = start; return Ok(None); },
+            Err(err) => return Err(err),
+        };
+        match self.decode_properties(buff_reader) {
+            Ok(Some(())) => {},
+            Ok(None) => { buff_reader.position = start; return Ok(None); },
+            Err(err) => return Err(err),
         }
-        self.ack_flags = buff_reader.readU8().unwrap();
-        self.connect_reason_code = buff_reader.readU8().unwrap();
-        self.decode_properties(buff_reader);
+        return Ok(Some(()));
+    }
+
+    /// Encodes this CONNACK packet into `buffer`, back-patching the remaining-length varint
+    /// once the variable header's actual size is known. Returns the number of Bytes written.
+    pub fn encode_connack_packet(&self, buffer: &mut [u8]) -> Result<usize, ParseError> {
+        let mut body_len: usize = 0;
+        match require_capacity(buffer, HEADER_RESERVE + body_len + 2) {
+            Ok(()) => {},
+            Err(err) => return Err(err),
+        };
+        buffer[HEADER_RESERVE + body_len] = self.ack_flags;
+        body_len = body_len + 1;
+        buffer[HEADER_RESERVE + body_len] = self.connect_reason_code;
+        body_len = body_len + 1;
+
+        let (property_len_bytes, property_len_size) = match VariableByteIntegerEncoder::encode(self.property_len) {
+            Ok(res) => res,
+            Err(err) => return Err(err),
+        };
+        match require_capacity(buffer, HEADER_RESERVE + body_len + property_len_size) {
+            Ok(()) => {},
+            Err(err) => return Err(err),
+        };
+        buffer[HEADER_RESERVE + body_len..HEADER_RESERVE + body_len + property_len_size]
+            .copy_from_slice(&property_len_bytes[..property_len_size]);
+        body_len = body_len + property_len_size;
+        for property in self.properties.iter() {
+            match require_capacity(buffer, HEADER_RESERVE + body_len) {
+                Ok(()) => {},
+                Err(err) => return Err(err),
+            };
+            let written = match property.encode(&mut buffer[HEADER_RESERVE + body_len..]) {
+                Ok(res) => res,
+                Err(err) => return Err(err),
+            };
+            body_len = body_len + written;
+        }
+
+        let (remain_len_bytes, remain_len_size) = match VariableByteIntegerEncoder::encode(body_len as u32) {
+            Ok(res) => res,
+            Err(err) => return Err(err),
+        };
+        let new_body_start = 1 + remain_len_size;
+        buffer.copy_within(HEADER_RESERVE..HEADER_RESERVE + body_len, new_body_start);
+        buffer[0] = self.fixed_header;
+        buffer[1..1 + remain_len_size].copy_from_slice(&remain_len_bytes[..remain_len_size]);
+
+        return Ok(1 + remain_len_size + body_len);
+    }
+
+    /// Copies this packet's scalar fields into a fully owned struct with no buffer lifetime, so
+    /// it can be queued (e.g. a retained CONNACK reason) while the receive buffer is reused for
+    /// the next frame. `Property` has no owned form yet, so a CONNACK carrying any properties
+    /// (e.g. a reason string) can't be safely made owned without silently losing them - this
+    /// fails with `ParseError::PropertyListFull` rather than drop them; read `self.properties`
+    /// from the borrowed packet first if you need them.
+    #[cfg(feature = "owned")]
+    pub fn to_owned(&self) -> Result<ConnackPacketOwned, ParseError> {
+        if !self.properties.is_empty() {
+            log::error!("Cannot produce an owned CONNACK packet while properties are present");
+            return Err(ParseError::PropertyListFull);
+        }
+        return Ok(ConnackPacketOwned {
+            fixed_header: self.fixed_header,
+            remain_len: self.remain_len,
+            ack_flags: self.ack_flags,
+            connect_reason_code: self.connect_reason_code,
+            property_len: self.property_len,
+        });
+    }
+}
+
+/// Owned counterpart to `ConnackPacket` with no buffer lifetime. See `ConnackPacket::to_owned`.
+#[cfg(feature = "owned")]
+pub struct ConnackPacketOwned {
+    pub fixed_header: u8,
+    pub remain_len: u32,
+    pub ack_flags: u8,
+    pub connect_reason_code: u8,
+    pub property_len: u32,
+}
+
+impl<'a> FixedHeaderPacket for ConnackPacket<'a> {
+    fn set_fixed_header(&mut self, value: u8) {
+        self.fixed_header = value;
+    }
+    fn set_remain_len(&mut self, value: u32) {
+        self.remain_len = value;
     }
 }
 
 impl<'a> Packet<'a> for ConnackPacket<'a> {
     fn decode(& mut self, buff_reader: & mut BuffReader<'a>) {
-        self.decode_connack_packet(buff_reader);
+        match self.decode_connack_packet(buff_reader) {
+            Ok(Some(())) => {},
+            Ok(None) => log::debug!("CONNACK packet is not fully buffered yet"),
+            Err(_err) => log::error!("Problem during CONNACK packet decoding"),
+        }
     }
     fn encode(& mut self, buffer: & mut [u8]) {
+        match self.encode_connack_packet(buffer) {
+            Ok(_len) => {},
+            Err(_err) => log::error!("Problem during CONNACK packet encoding"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn empty_connack<'a>() -> ConnackPacket<'a> {
+        ConnackPacket {
+            fixed_header: 0,
+            remain_len: 0,
+            ack_flags: 0,
+            connect_reason_code: 0,
+            property_len: 0,
+            properties: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn decode_connack_packet_is_resumable_across_a_split_buffer() {
+        // fixed header, remaining length = 3, ack flags, reason code, property_len = 0
+        let full: [u8; 5] = [0x20, 0x03, 0x00, 0x00, 0x00];
+
+        let mut packet = empty_connack();
+        let mut buff_reader = BuffReader::new(&full[..3]);
+        assert_eq!(packet.decode_connack_packet(&mut buff_reader), Ok(None));
+        assert_eq!(buff_reader.position, 0);
 
+        let mut buff_reader = BuffReader::new(&full);
+        assert_eq!(packet.decode_connack_packet(&mut buff_reader), Ok(Some(())));
+        assert_eq!(packet.ack_flags, 0x00);
+        assert_eq!(packet.connect_reason_code, 0x00);
     }
 }
\ No newline at end of file
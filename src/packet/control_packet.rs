@@ -1,15 +1,35 @@
 use super::property::Property;
 use super::packet_type::PacketType;
+use crate::encoding::variable_byte_integer::VariableByteIntegerEncoder;
 use crate::utils::buffer_reader::BuffReader;
 use crate::utils::buffer_reader::EncodedString;
 use crate::utils::buffer_reader::BinaryData;
 use crate::utils::buffer_reader::ParseError;
+#[cfg(feature = "owned")]
+use crate::utils::buffer_reader::EncodedStringOwned;
+#[cfg(feature = "owned")]
+use crate::utils::buffer_reader::BinaryDataOwned;
 use crate::packet::mqtt_packet::Packet;
+use crate::packet::FixedHeaderPacket;
 use heapless::Vec;
 
 pub const MAX_PROPERTIES: usize = 18;
 pub const MAX_WILL_PROPERTIES: usize = 7;
 
+/// Bytes reserved in front of the encoded body for the fixed header (1 Byte) and the worst
+/// case 4-Byte remaining-length varint, so the body can be written once and the varint
+/// back-patched in front of it once its own size is known.
+const HEADER_RESERVE: usize = 5;
+
+/// Returns `ParseError::IndexOutOfBounce` instead of letting a later write slice-index panic
+/// when the caller handed `encode_control_packet` a buffer too small to hold `needed` Bytes.
+fn require_capacity(buffer: &[u8], needed: usize) -> Result<(), ParseError> {
+    if buffer.len() < needed {
+        return Err(ParseError::IndexOutOfBounce);
+    }
+    return Ok(());
+}
+
 pub struct ControlPacket<'a> {
     // 7 - 4 mqtt control packet type, 3-0 flagy
     pub fixed_header: u8,
@@ -79,87 +99,389 @@ impl<'a> ControlPacket<'a> {
         self.fixed_header = cur_type | flags;
     }
 
-    pub fn decode_fixed_header(& mut self, buff_reader: & mut BuffReader) -> PacketType {
-        let first_byte: u8 = buff_reader.readU8().unwrap();
-        self.fixed_header = first_byte;
-        self.remain_len = buff_reader.readVariableByteInt().unwrap();
-        return PacketType::from(self.fixed_header);
-    }
-
-    pub fn decode_properties(& mut self, buff_reader: & mut BuffReader<'a>) {
-
-        self.property_len = buff_reader.readVariableByteInt().unwrap();
+    /// Decodes the CONNECT property list, pushing each into `self.properties` up to
+    /// `MAX_PROPERTIES`. A partially-buffered property rewinds to the list's start and
+    /// returns `Ok(None)` rather than consuming anything.
+    pub fn decode_properties(& mut self, buff_reader: & mut BuffReader<'a>) -> Result<Option<()>, ParseError> {
+        let start = buff_reader.position;
+        // A retry after `Ok(None)` decodes the whole list again, so drop what an earlier try pushed.
+        self.properties.clear();
+        self.property_len = match buff_reader.read_variable_byte_int() {
+            Ok(Some(res)) => res,
+            Ok(None) => { buff_reader.position = start; return Ok(None); },
+            Err(err) => return Err(err),
+        };
         let mut x: u32 = 0;
-        let mut prop: Result<Property, ParseError>;
-        loop {
-            let mut res: Property;
-            prop = Property::decode(buff_reader);
-            if let Ok(res) = prop {
-                log::info!("Parsed property {:?}", res);
-                x = x + res.len() as u32 + 1;
-                self.properties.push(res);
-            } else {
-                // error handlo
-                log::error!("Problem during property decoding");
-            }
-            
-            if x == self.property_len {
-                break;
+        while x < self.property_len {
+            match Property::decode(buff_reader) {
+                Ok(Some(res)) => {
+                    log::info!("Parsed property {:?}", res);
+                    x = x + res.len() as u32 + 1;
+                    if x > self.property_len {
+                        log::error!("Decoded properties overran declared property_len");
+                        return Err(ParseError::DecodingError);
+                    }
+                    match self.properties.push(res) {
+                        Ok(()) => {},
+                        Err(_res) => {
+                            log::error!("Too many properties for MAX_PROPERTIES capacity");
+                            return Err(ParseError::PropertyListFull);
+                        },
+                    }
+                },
+                Ok(None) => {
+                    buff_reader.position = start;
+                    return Ok(None);
+                },
+                Err(err) => {
+                    log::error!("Problem during property decoding");
+                    return Err(err);
+                },
             }
         }
+        return Ok(Some(()));
     }
 
-    pub fn decode_will_properties(& mut self, buff_reader: & mut BuffReader<'a>) {
+    /// Decodes the CONNECT will-property list, mirroring `decode_properties`.
+    pub fn decode_will_properties(& mut self, buff_reader: & mut BuffReader<'a>) -> Result<Option<()>, ParseError> {
         //todo: need to check if we are parsing only will properties
-        let will_property_len = buff_reader.readVariableByteInt().unwrap();
+        let start = buff_reader.position;
+        // A retry after `Ok(None)` decodes the whole list again, so drop what an earlier try pushed.
+        self.will_properties.clear();
+        let will_property_len = match buff_reader.read_variable_byte_int() {
+            Ok(Some(res)) => res,
+            Ok(None) => { buff_reader.position = start; return Ok(None); },
+            Err(err) => return Err(err),
+        };
+        self.will_property_len = will_property_len;
         let mut x: u32 = 0;
-        let mut prop: Result<Property, ParseError>;
-        loop {
-            let mut res: Property;
-            prop = Property::decode(buff_reader);
-            if let Ok(res) = prop {
-                log::info!("Will property parsed: {:?}", res);
-                x = x + res.len() as u32 + 1;
-                self.will_properties.push(res);
-            } else {
-                // error handlo
-                log::error!("Problem during property decoding");
-            }
-            
-            if x == will_property_len {
-                break;
+        while x < will_property_len {
+            match Property::decode(buff_reader) {
+                Ok(Some(res)) => {
+                    log::info!("Will property parsed: {:?}", res);
+                    x = x + res.len() as u32 + 1;
+                    if x > will_property_len {
+                        log::error!("Decoded will properties overran declared will_property_len");
+                        return Err(ParseError::DecodingError);
+                    }
+                    match self.will_properties.push(res) {
+                        Ok(()) => {},
+                        Err(_res) => {
+                            log::error!("Too many will properties for MAX_WILL_PROPERTIES capacity");
+                            return Err(ParseError::PropertyListFull);
+                        },
+                    }
+                },
+                Ok(None) => {
+                    buff_reader.position = start;
+                    return Ok(None);
+                },
+                Err(err) => {
+                    log::error!("Problem during property decoding");
+                    return Err(err);
+                },
             }
         }
+        return Ok(Some(()));
     }
 
-    pub fn decode_payload(& mut self, buff_reader: & mut BuffReader<'a>) {
-        self.client_id = buff_reader.readString().unwrap();
-        if self.connect_flags & (1 << 2) == 1 {
-            self.decode_will_properties(buff_reader);
-            self.will_topic = buff_reader.readString().unwrap();
-            self.will_payload = buff_reader.readBinary().unwrap();
+    pub fn decode_payload(& mut self, buff_reader: & mut BuffReader<'a>) -> Result<Option<()>, ParseError> {
+        let start = buff_reader.position;
+        self.client_id = match buff_reader.read_string() {
+            Ok(Some(res)) => res,
+            Ok(None) => { buff_reader.position = start; return Ok(None); },
+            Err(err) => return Err(err),
+        };
+        if self.connect_flags & (1 << 2) != 0 {
+            match self.decode_will_properties(buff_reader) {
+                Ok(Some(())) => {},
+                Ok(None) => { buff_reader.position = start; return Ok(None); },
+                Err(err) => return Err(err),
+            }
+            self.will_topic = match buff_reader.read_string() {
+                Ok(Some(res)) => res,
+                Ok(None) => { buff_reader.position = start; return Ok(None); },
+                Err(err) => return Err(err),
+            };
+            self.will_payload = match buff_reader.read_binary() {
+                Ok(Some(res)) => res,
+                Ok(None) => { buff_reader.position = start; return Ok(None); },
+                Err(err) => return Err(err),
+            };
         }
-        
-        if self.connect_flags & (1 << 7) == 1 {
-            self.username = buff_reader.readString().unwrap();
+
+        if self.connect_flags & (1 << 7) != 0 {
+            self.username = match buff_reader.read_string() {
+                Ok(Some(res)) => res,
+                Ok(None) => { buff_reader.position = start; return Ok(None); },
+                Err(err) => return Err(err),
+            };
         }
-        if self.connect_flags & (1 << 6) == 1 {
-            self.password = buff_reader.readBinary().unwrap();
+        if self.connect_flags & (1 << 6) != 0 {
+            self.password = match buff_reader.read_binary() {
+                Ok(Some(res)) => res,
+                Ok(None) => { buff_reader.position = start; return Ok(None); },
+                Err(err) => return Err(err),
+            };
         }
+        return Ok(Some(()));
     }
 
-    pub fn decode_control_packet(& mut self, buff_reader: & mut BuffReader<'a>) {
-        if self.decode_fixed_header(buff_reader) != (PacketType::Connect).into() {
+    /// Walks the fixed header, variable header, properties and payload of a CONNECT packet in
+    /// order. If any of them isn't fully buffered yet, rewinds to where this call started and
+    /// returns `Ok(None)` so the caller can just buffer more Bytes and call this again.
+    pub fn decode_control_packet(& mut self, buff_reader: & mut BuffReader<'a>) -> Result<Option<()>, ParseError> {
+        let start = buff_reader.position;
+        let packet_type = match self.decode_fixed_header(buff_reader) {
+            Ok(Some(res)) => res,
+            Ok(None) => { buff_reader.position = start; return Ok(None); },
+            Err(err) => return Err(err),
+        };
+        if packet_type != (PacketType::Connect).into() {
             log::error!("Packet you are trying to decode is not CONNECT packet!");
         }
         self.packet_identifier = 0;
-        self.protocol_name_len = buff_reader.readU16().unwrap();
-        self.protocol_name = buff_reader.readU32().unwrap();
-        self.protocol_version = buff_reader.readU8().unwrap();
-        self.connect_flags = buff_reader.readU8().unwrap();
-        self.keep_alive = buff_reader.readU16().unwrap();
-        self.decode_properties(buff_reader);
-        self.decode_payload(buff_reader);
+        self.protocol_name_len = match buff_reader.read_u16() {
+            Ok(Some(res)) => res,
+            Ok(None) => { buff_reader.position = start; return Ok(None); },
+            Err(err) => return Err(err),
+        };
+        self.protocol_name = match buff_reader.read_u32() {
+            Ok(Some(res)) => res,
+            Ok(None) => { buff_reader.position = start; return Ok(None); },
+            Err(err) => return Err(err),
+        };
+        self.protocol_version = match buff_reader.read_u8() {
+            Ok(Some(res)) => res,
+            Ok(None) => { buff_reader.position = start; return Ok(None); },
+            Err(err) => return Err(err),
+        };
+        self.connect_flags = match buff_reader.read_u8() {
+            Ok(Some(res)) => res,
+            Ok(None) => { buff_reader.position = start; return Ok(None); },
+            Err(err) => return Err(err),
+        };
+        self.keep_alive = match buff_reader.read_u16() {
+            Ok(Some(res)) => res,
+            Ok(None) => { buff_reader.position = start; return Ok(None); },
+            Err(err) => return Err(err),
+        };
+        match self.decode_properties(buff_reader) {
+            Ok(Some(())) => {},
+            Ok(None) => { buff_reader.position = start; return Ok(None); },
+            Err(err) => return Err(err),
+        }
+        match self.decode_payload(buff_reader) {
+            Ok(Some(())) => {},
+            Ok(None) => { buff_reader.position = start; return Ok(None); },
+            Err(err) => return Err(err),
+        }
+        return Ok(Some(()));
+    }
+
+    /// Encodes this CONNECT packet into `buffer`, back-patching the remaining-length varint
+    /// once the variable header and payload's actual size is known. Returns the number of
+    /// Bytes written.
+    pub fn encode_control_packet(&self, buffer: &mut [u8]) -> Result<usize, ParseError> {
+        let mut body_len: usize = 0;
+        match require_capacity(buffer, HEADER_RESERVE + body_len + 2) {
+            Ok(()) => {},
+            Err(err) => return Err(err),
+        };
+        buffer[HEADER_RESERVE + body_len..HEADER_RESERVE + body_len + 2]
+            .copy_from_slice(&self.protocol_name_len.to_be_bytes());
+        body_len = body_len + 2;
+        match require_capacity(buffer, HEADER_RESERVE + body_len + 4) {
+            Ok(()) => {},
+            Err(err) => return Err(err),
+        };
+        buffer[HEADER_RESERVE + body_len..HEADER_RESERVE + body_len + 4]
+            .copy_from_slice(&self.protocol_name.to_be_bytes());
+        body_len = body_len + 4;
+        match require_capacity(buffer, HEADER_RESERVE + body_len + 4) {
+            Ok(()) => {},
+            Err(err) => return Err(err),
+        };
+        buffer[HEADER_RESERVE + body_len] = self.protocol_version;
+        body_len = body_len + 1;
+        buffer[HEADER_RESERVE + body_len] = self.connect_flags;
+        body_len = body_len + 1;
+        buffer[HEADER_RESERVE + body_len..HEADER_RESERVE + body_len + 2]
+            .copy_from_slice(&self.keep_alive.to_be_bytes());
+        body_len = body_len + 2;
+
+        let (property_len_bytes, property_len_size) = match VariableByteIntegerEncoder::encode(self.property_len) {
+            Ok(res) => res,
+            Err(err) => return Err(err),
+        };
+        match require_capacity(buffer, HEADER_RESERVE + body_len + property_len_size) {
+            Ok(()) => {},
+            Err(err) => return Err(err),
+        };
+        buffer[HEADER_RESERVE + body_len..HEADER_RESERVE + body_len + property_len_size]
+            .copy_from_slice(&property_len_bytes[..property_len_size]);
+        body_len = body_len + property_len_size;
+        for property in self.properties.iter() {
+            match require_capacity(buffer, HEADER_RESERVE + body_len) {
+                Ok(()) => {},
+                Err(err) => return Err(err),
+            };
+            let written = match property.encode(&mut buffer[HEADER_RESERVE + body_len..]) {
+                Ok(res) => res,
+                Err(err) => return Err(err),
+            };
+            body_len = body_len + written;
+        }
+
+        match require_capacity(buffer, HEADER_RESERVE + body_len + self.client_id.len() as usize) {
+            Ok(()) => {},
+            Err(err) => return Err(err),
+        };
+        body_len = body_len + self.client_id.encode(&mut buffer[HEADER_RESERVE + body_len..]);
+
+        if self.connect_flags & (1 << 2) != 0 {
+            let (will_property_len_bytes, will_property_len_size) =
+                match VariableByteIntegerEncoder::encode(self.will_property_len) {
+                    Ok(res) => res,
+                    Err(err) => return Err(err),
+                };
+            match require_capacity(buffer, HEADER_RESERVE + body_len + will_property_len_size) {
+                Ok(()) => {},
+                Err(err) => return Err(err),
+            };
+            buffer[HEADER_RESERVE + body_len..HEADER_RESERVE + body_len + will_property_len_size]
+                .copy_from_slice(&will_property_len_bytes[..will_property_len_size]);
+            body_len = body_len + will_property_len_size;
+            for property in self.will_properties.iter() {
+                match require_capacity(buffer, HEADER_RESERVE + body_len) {
+                    Ok(()) => {},
+                    Err(err) => return Err(err),
+                };
+                let written = match property.encode(&mut buffer[HEADER_RESERVE + body_len..]) {
+                    Ok(res) => res,
+                    Err(err) => return Err(err),
+                };
+                body_len = body_len + written;
+            }
+            match require_capacity(buffer, HEADER_RESERVE + body_len + self.will_topic.len() as usize) {
+                Ok(()) => {},
+                Err(err) => return Err(err),
+            };
+            body_len = body_len + self.will_topic.encode(&mut buffer[HEADER_RESERVE + body_len..]);
+            match require_capacity(buffer, HEADER_RESERVE + body_len + self.will_payload.len() as usize) {
+                Ok(()) => {},
+                Err(err) => return Err(err),
+            };
+            body_len = body_len + self.will_payload.encode(&mut buffer[HEADER_RESERVE + body_len..]);
+        }
+
+        if self.connect_flags & (1 << 7) != 0 {
+            match require_capacity(buffer, HEADER_RESERVE + body_len + self.username.len() as usize) {
+                Ok(()) => {},
+                Err(err) => return Err(err),
+            };
+            body_len = body_len + self.username.encode(&mut buffer[HEADER_RESERVE + body_len..]);
+        }
+        if self.connect_flags & (1 << 6) != 0 {
+            match require_capacity(buffer, HEADER_RESERVE + body_len + self.password.len() as usize) {
+                Ok(()) => {},
+                Err(err) => return Err(err),
+            };
+            body_len = body_len + self.password.encode(&mut buffer[HEADER_RESERVE + body_len..]);
+        }
+
+        let (remain_len_bytes, remain_len_size) = match VariableByteIntegerEncoder::encode(body_len as u32) {
+            Ok(res) => res,
+            Err(err) => return Err(err),
+        };
+        let new_body_start = 1 + remain_len_size;
+        buffer.copy_within(HEADER_RESERVE..HEADER_RESERVE + body_len, new_body_start);
+        buffer[0] = self.fixed_header;
+        buffer[1..1 + remain_len_size].copy_from_slice(&remain_len_bytes[..remain_len_size]);
+
+        return Ok(1 + remain_len_size + body_len);
+    }
+
+    /// Copies every string/binary payload field out of the borrowed buffer into bounded owned
+    /// storage (all sharing capacity `N`), so the resulting CONNECT packet no longer needs to
+    /// outlive the network buffer it was decoded from. `Property` has no owned form yet, so a
+    /// CONNECT (or will message) carrying any properties can't be safely made owned without
+    /// silently losing them - this fails with `ParseError::PropertyListFull` rather than drop
+    /// them; read `self.properties`/`self.will_properties` from the borrowed packet first if
+    /// you need them.
+    #[cfg(feature = "owned")]
+    pub fn to_owned<const N: usize>(&self) -> Result<ControlPacketOwned<N>, ParseError> {
+        if !self.properties.is_empty() || !self.will_properties.is_empty() {
+            log::error!("Cannot produce an owned CONNECT packet while properties are present");
+            return Err(ParseError::PropertyListFull);
+        }
+        let client_id = match self.client_id.to_owned() {
+            Ok(res) => res,
+            Err(err) => return Err(err),
+        };
+        let will_topic = match self.will_topic.to_owned() {
+            Ok(res) => res,
+            Err(err) => return Err(err),
+        };
+        let will_payload = match self.will_payload.to_owned() {
+            Ok(res) => res,
+            Err(err) => return Err(err),
+        };
+        let username = match self.username.to_owned() {
+            Ok(res) => res,
+            Err(err) => return Err(err),
+        };
+        let password = match self.password.to_owned() {
+            Ok(res) => res,
+            Err(err) => return Err(err),
+        };
+        return Ok(ControlPacketOwned {
+            fixed_header: self.fixed_header,
+            remain_len: self.remain_len,
+            packet_identifier: self.packet_identifier,
+            protocol_name_len: self.protocol_name_len,
+            protocol_name: self.protocol_name,
+            protocol_version: self.protocol_version,
+            connect_flags: self.connect_flags,
+            keep_alive: self.keep_alive,
+            property_len: self.property_len,
+            client_id,
+            will_property_len: self.will_property_len,
+            will_topic,
+            will_payload,
+            username,
+            password,
+        });
+    }
+}
+
+/// Owned counterpart to `ControlPacket` (CONNECT) with no buffer lifetime. See
+/// `ControlPacket::to_owned`.
+#[cfg(feature = "owned")]
+pub struct ControlPacketOwned<const N: usize> {
+    pub fixed_header: u8,
+    pub remain_len: u32,
+    pub packet_identifier: u16,
+    pub protocol_name_len: u16,
+    pub protocol_name: u32,
+    pub protocol_version: u8,
+    pub connect_flags: u8,
+    pub keep_alive: u16,
+    pub property_len: u32,
+    pub client_id: EncodedStringOwned<N>,
+    pub will_property_len: u32,
+    pub will_topic: EncodedStringOwned<N>,
+    pub will_payload: BinaryDataOwned<N>,
+    pub username: EncodedStringOwned<N>,
+    pub password: BinaryDataOwned<N>,
+}
+
+impl<'a> FixedHeaderPacket for ControlPacket<'a> {
+    fn set_fixed_header(&mut self, value: u8) {
+        self.fixed_header = value;
+    }
+    fn set_remain_len(&mut self, value: u32) {
+        self.remain_len = value;
     }
 }
 
@@ -170,6 +492,52 @@ impl<'a> Packet<'a> for ControlPacket<'a> {
     }
 
     fn encode(& mut self, buffer: & mut [u8]) {
+        match self.encode_control_packet(buffer) {
+            Ok(_len) => {},
+            Err(_err) => log::error!("Problem during CONNECT packet encoding"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // CONNECT with protocol name "MQTT", version 5, no flags, no properties, client_id "ab".
+    // Mid-property-list resumability isn't covered here: that path decodes real `Property`
+    // values via `Property::decode`, which has no implementation in this snapshot to build
+    // test data against.
+    const CONNECT_BYTES: [u8; 17] = [
+        0x10, 0x0F, // fixed header, remaining length = 15
+        0x00, 0x04, 0x4D, 0x51, 0x54, 0x54, // protocol name len + "MQTT"
+        0x05, // protocol version
+        0x00, // connect flags
+        0x00, 0x00, // keep alive
+        0x00, // property_len = 0
+        0x00, 0x02, 0x61, 0x62, // client_id len = 2, "ab"
+    ];
+
+    fn empty_control_packet<'a>() -> ControlPacket<'a> {
+        ControlPacket::clean(Vec::new(), Vec::new())
+    }
+
+    #[test]
+    fn decode_control_packet_is_resumable_mid_variable_header() {
+        let mut packet = empty_control_packet();
+        let mut buff_reader = BuffReader::new(&CONNECT_BYTES[..6]);
+        assert_eq!(packet.decode_control_packet(&mut buff_reader), Ok(None));
+        assert_eq!(buff_reader.position, 0);
+    }
+
+    #[test]
+    fn decode_control_packet_is_resumable_mid_payload() {
+        let mut packet = empty_control_packet();
+        let mut buff_reader = BuffReader::new(&CONNECT_BYTES[..15]);
+        assert_eq!(packet.decode_control_packet(&mut buff_reader), Ok(None));
+        assert_eq!(buff_reader.position, 0);
 
+        let mut buff_reader = BuffReader::new(&CONNECT_BYTES);
+        assert_eq!(packet.decode_control_packet(&mut buff_reader), Ok(Some(())));
+        assert_eq!(packet.client_id.string, "ab");
     }
 }
\ No newline at end of file
@@ -0,0 +1,91 @@
+pub mod connack_packet;
+pub mod control_packet;
+
+use crate::packet::connack_packet::ConnackPacket;
+use crate::packet::control_packet::ControlPacket;
+use crate::packet::packet_type::PacketType;
+use crate::utils::buffer_reader::{BuffReader, ParseError};
+use heapless::Vec;
+
+/// Shared fixed-header decode for every packet type that stores a `fixed_header`/`remain_len`
+/// pair (so far `ControlPacket` and `ConnackPacket`), so the parsing logic lives in one place
+/// instead of being copy-pasted into each decoder. Implementors only need to supply the two
+/// setters; `decode_fixed_header` itself is a default method.
+pub trait FixedHeaderPacket {
+    fn set_fixed_header(&mut self, value: u8);
+    fn set_remain_len(&mut self, value: u32);
+
+    /// Reads the packet type Byte and remaining-length varint. `Ok(None)` means the two have
+    /// not both fully arrived yet; `buff_reader.position` is left untouched so the caller can
+    /// retry.
+    fn decode_fixed_header(&mut self, buff_reader: &mut BuffReader) -> Result<Option<PacketType>, ParseError> {
+        let start = buff_reader.position;
+        let first_byte: u8 = match buff_reader.read_u8() {
+            Ok(Some(res)) => res,
+            Ok(None) => { buff_reader.position = start; return Ok(None); },
+            Err(err) => return Err(err),
+        };
+        self.set_fixed_header(first_byte);
+        let remain_len = match buff_reader.read_variable_byte_int() {
+            Ok(Some(res)) => res,
+            Ok(None) => { buff_reader.position = start; return Ok(None); },
+            Err(err) => return Err(err),
+        };
+        self.set_remain_len(remain_len);
+        return Ok(Some(PacketType::from(first_byte)));
+    }
+}
+
+/// Dispatches over every packet type this crate knows how to decode. `AnyPacket::decode` peeks
+/// the fixed header to learn which packet is incoming and delegates to that variant's own
+/// decoder, so a receive loop can decode an arbitrary incoming packet without knowing its type
+/// up front. Only CONNECT/CONNACK are covered so far - PUBLISH, SUBACK and the rest don't have
+/// a decoder struct anywhere in this crate yet, so adding their dispatch arms is follow-up work,
+/// not something this enum can do on its own. Named `AnyPacket` rather than `Packet` to avoid
+/// colliding with the `mqtt_packet::Packet` trait that `ControlPacket`/`ConnackPacket` already
+/// implement.
+pub enum AnyPacket<'a> {
+    Connect(ControlPacket<'a>),
+    Connack(ConnackPacket<'a>),
+}
+
+impl<'a> AnyPacket<'a> {
+    /// Peeks the first Byte of the fixed header without consuming it, maps it to a
+    /// `PacketType`, and delegates to that variant's decoder. Returns `Ok(None)` (buffer left
+    /// untouched) if even the first Byte of the fixed header hasn't arrived yet.
+    pub fn decode(buff_reader: &mut BuffReader<'a>) -> Result<Option<Self>, ParseError> {
+        let first_byte = match buff_reader.peek_u8() {
+            Some(res) => res,
+            None => return Ok(None),
+        };
+        match PacketType::from(first_byte) {
+            PacketType::Connect => {
+                let mut packet = ControlPacket::clean(Vec::new(), Vec::new());
+                match packet.decode_control_packet(buff_reader) {
+                    Ok(Some(())) => Ok(Some(AnyPacket::Connect(packet))),
+                    Ok(None) => Ok(None),
+                    Err(err) => Err(err),
+                }
+            },
+            PacketType::Connack => {
+                let mut packet = ConnackPacket {
+                    fixed_header: 0,
+                    remain_len: 0,
+                    ack_flags: 0,
+                    connect_reason_code: 0,
+                    property_len: 0,
+                    properties: Vec::new(),
+                };
+                match packet.decode_connack_packet(buff_reader) {
+                    Ok(Some(())) => Ok(Some(AnyPacket::Connack(packet))),
+                    Ok(None) => Ok(None),
+                    Err(err) => Err(err),
+                }
+            },
+            _ => {
+                log::error!("AnyPacket::decode does not support this packet type yet");
+                Err(ParseError::IdNotFound)
+            },
+        }
+    }
+}
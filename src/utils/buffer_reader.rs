@@ -43,6 +43,48 @@ impl EncodedString<'_> {
     pub fn len(&self) -> u16 {
         return self.len + 2;
     }
+
+    /// Encodes the 2-Byte length prefix followed by the string's UTF-8 Bytes into `buffer`.
+    /// Returns the number of Bytes written (always equal to `self.len()`).
+    pub fn encode(&self, buffer: &mut [u8]) -> usize {
+        let str_bytes = self.string.as_bytes();
+        buffer[0..2].copy_from_slice(&self.len.to_be_bytes());
+        buffer[2..2 + str_bytes.len()].copy_from_slice(str_bytes);
+        return 2 + str_bytes.len();
+    }
+
+    /// Copies this borrowed string into a bounded owned buffer so it no longer needs to
+    /// outlive the network buffer it was decoded from. Fails with `ParseError::EncodingError`
+    /// if the string doesn't fit in `N` Bytes.
+    #[cfg(feature = "owned")]
+    pub fn to_owned<const N: usize>(&self) -> Result<EncodedStringOwned<N>, ParseError> {
+        let mut string: heapless::String<N> = heapless::String::new();
+        if string.push_str(self.string).is_err() {
+            return Err(ParseError::EncodingError);
+        }
+        return Ok(EncodedStringOwned {
+            string,
+            len: self.len,
+        });
+    }
+}
+
+/// Owned counterpart to `EncodedString`, backed by a bounded `heapless::String<N>` instead of a
+/// borrow into the network buffer. Lets callers queue a decoded packet (or a retained will
+/// message) while the receive buffer is reused for the next frame.
+#[cfg(feature = "owned")]
+#[derive(Debug, Clone)]
+pub struct EncodedStringOwned<const N: usize> {
+    pub string: heapless::String<N>,
+    pub len: u16,
+}
+
+#[cfg(feature = "owned")]
+impl<const N: usize> EncodedStringOwned<N> {
+    /// Return length of string
+    pub fn len(&self) -> u16 {
+        return self.len + 2;
+    }
 }
 
 /// Binary data represents `Binary data` in MQTTv5 protocol
@@ -61,6 +103,47 @@ impl BinaryData<'_> {
     pub fn len(&self) -> u16 {
         return self.len + 2;
     }
+
+    /// Encodes the 2-Byte length prefix followed by the raw Bytes into `buffer`. Returns the
+    /// number of Bytes written (always equal to `self.len()`).
+    pub fn encode(&self, buffer: &mut [u8]) -> usize {
+        let data = &self.bin[..self.len as usize];
+        buffer[0..2].copy_from_slice(&self.len.to_be_bytes());
+        buffer[2..2 + data.len()].copy_from_slice(data);
+        return 2 + data.len();
+    }
+
+    /// Copies this borrowed Byte array into a bounded owned buffer so it no longer needs to
+    /// outlive the network buffer it was decoded from. Fails with `ParseError::EncodingError`
+    /// if the data doesn't fit in `N` Bytes.
+    #[cfg(feature = "owned")]
+    pub fn to_owned<const N: usize>(&self) -> Result<BinaryDataOwned<N>, ParseError> {
+        let mut bin: heapless::Vec<u8, N> = heapless::Vec::new();
+        if bin.extend_from_slice(self.bin).is_err() {
+            return Err(ParseError::EncodingError);
+        }
+        return Ok(BinaryDataOwned {
+            bin,
+            len: self.len,
+        });
+    }
+}
+
+/// Owned counterpart to `BinaryData`, backed by a bounded `heapless::Vec<u8, N>` instead of a
+/// borrow into the network buffer.
+#[cfg(feature = "owned")]
+#[derive(Debug, Clone)]
+pub struct BinaryDataOwned<const N: usize> {
+    pub bin: heapless::Vec<u8, N>,
+    pub len: u16,
+}
+
+#[cfg(feature = "owned")]
+impl<const N: usize> BinaryDataOwned<N> {
+    /// Returns length of Byte array
+    pub fn len(&self) -> u16 {
+        return self.len + 2;
+    }
 }
 
 /// String pair struct represents `String pair` in MQTTv5 (2 UTF-8 encoded strings name-value)
@@ -76,6 +159,28 @@ impl StringPair<'_> {
         let ln = self.name.len() + self.value.len();
         return ln;
     }
+
+    /// Copies both strings of the pair into bounded owned buffers.
+    #[cfg(feature = "owned")]
+    pub fn to_owned<const N: usize>(&self) -> Result<StringPairOwned<N>, ParseError> {
+        let name = match self.name.to_owned() {
+            Ok(res) => res,
+            Err(err) => return Err(err),
+        };
+        let value = match self.value.to_owned() {
+            Ok(res) => res,
+            Err(err) => return Err(err),
+        };
+        return Ok(StringPairOwned { name, value });
+    }
+}
+
+/// Owned counterpart to `StringPair`, backed by `EncodedStringOwned<N>` on both sides.
+#[cfg(feature = "owned")]
+#[derive(Debug, Clone)]
+pub struct StringPairOwned<const N: usize> {
+    pub name: EncodedStringOwned<N>,
+    pub value: EncodedStringOwned<N>,
 }
 
 /// Topic filter serves as bound for topic selection and subscription options for `SUBSCRIPTION` packet
@@ -98,7 +203,7 @@ impl TopicFilter<'_> {
     }
 }
 
-#[derive(core::fmt::Debug, Clone)]
+#[derive(core::fmt::Debug, Clone, PartialEq)]
 pub enum ParseError {
     Utf8Error,
     IndexOutOfBounce,
@@ -106,6 +211,7 @@ pub enum ParseError {
     IdNotFound,
     EncodingError,
     DecodingError,
+    PropertyListFull,
 }
 
 /// Buff reader is reading corresponding types from buffer (Byte array) and stores current position
@@ -127,61 +233,99 @@ impl<'a> BuffReader<'a> {
         };
     }
 
-    /// Variable byte integer can be 1-4 Bytes long. Buffer reader takes all 4 Bytes at first and
-    /// than check what is true length of varbyteint and increment cursor by that
-    pub fn read_variable_byte_int(&mut self) -> Result<u32, ParseError> {
-        let variable_byte_integer: [u8; 4] = [
-            self.buffer[self.position],
-            self.buffer[self.position + 1],
-            self.buffer[self.position + 2],
-            self.buffer[self.position + 3],
-        ];
-        let mut len: usize = 1;
-        /// Everytime checking first bit of Byte which determines whenever there is continous Byte
-        if variable_byte_integer[0] & 0x80 == 1 {
+    /// Returns `true` if there are at least `len` unread Bytes left in the buffer. Every
+    /// `read_*` method that returns `Result<Option<_>, _>` checks this (directly or through the
+    /// readers it calls) so that a decode which is missing Bytes can bail out with `Ok(None)`
+    /// instead of panicking on a short slice. `read_message` does its own bounds check and
+    /// returns `ParseError::IndexOutOfBounce` instead.
+    fn require_length(&self, len: usize) -> bool {
+        self.buffer.len().saturating_sub(self.position) >= len
+    }
+
+    /// Peeks the next Byte without advancing `position`. Used by `AnyPacket::decode` to learn a
+    /// packet's type from its fixed header before committing to a concrete decoder.
+    pub fn peek_u8(&self) -> Option<u8> {
+        if self.require_length(1) {
+            Some(self.buffer[self.position])
+        } else {
+            None
+        }
+    }
+
+    /// Variable byte integer can be 1-4 Bytes long. Buffer reader peeks one Byte at a time,
+    /// following the continuation bit, and only consumes them once the full integer is known
+    /// to be buffered. Returns `Ok(None)` (leaving `position` untouched) when the continuation
+    /// Byte hasn't arrived yet.
+    pub fn read_variable_byte_int(&mut self) -> Result<Option<u32>, ParseError> {
+        let start = self.position;
+        let mut variable_byte_integer: [u8; 4] = [0; 4];
+        let mut len: usize = 0;
+        loop {
+            if !self.require_length(len + 1) {
+                return Ok(None);
+            }
+            let byte = self.buffer[start + len];
+            variable_byte_integer[len] = byte;
             len = len + 1;
-            if variable_byte_integer[1] & 0x80 == 1 {
-                len = len + 1;
-                if variable_byte_integer[2] & 0x80 == 1 {
-                    len = len + 1;
-                }
+            // Everytime checking first bit of Byte which determines whenever there is continous Byte
+            if byte & 0x80 == 0 {
+                break;
+            }
+            if len == 4 {
+                return Err(ParseError::VariableByteIntegerError);
             }
         }
         self.increment_position(len);
-        return VariableByteIntegerDecoder::decode(variable_byte_integer);
+        return match VariableByteIntegerDecoder::decode(variable_byte_integer) {
+            Ok(res) => Ok(Some(res)),
+            Err(err) => Err(err),
+        };
     }
 
     /// Reading u32 from buffer as `Big endian`
-    pub fn read_u32(&mut self) -> Result<u32, ParseError> {
-        let (int_bytes, rest) = self.buffer[self.position..].split_at(mem::size_of::<u32>());
+    pub fn read_u32(&mut self) -> Result<Option<u32>, ParseError> {
+        if !self.require_length(mem::size_of::<u32>()) {
+            return Ok(None);
+        }
+        let (int_bytes, _rest) = self.buffer[self.position..].split_at(mem::size_of::<u32>());
         let ret: u32 = u32::from_be_bytes(int_bytes.try_into().unwrap());
         self.increment_position(4);
-        return Ok(ret);
+        return Ok(Some(ret));
     }
 
     /// Reading u16 from buffer as `Big endinan`
-    pub fn read_u16(&mut self) -> Result<u16, ParseError> {
-        let (int_bytes, rest) = self.buffer[self.position..].split_at(mem::size_of::<u16>());
+    pub fn read_u16(&mut self) -> Result<Option<u16>, ParseError> {
+        if !self.require_length(mem::size_of::<u16>()) {
+            return Ok(None);
+        }
+        let (int_bytes, _rest) = self.buffer[self.position..].split_at(mem::size_of::<u16>());
         let ret: u16 = u16::from_be_bytes(int_bytes.try_into().unwrap());
         self.increment_position(2);
-        return Ok(ret);
+        return Ok(Some(ret));
     }
 
     /// Reading one byte from buffer as `Big endian`
-    pub fn read_u8(&mut self) -> Result<u8, ParseError> {
+    pub fn read_u8(&mut self) -> Result<Option<u8>, ParseError> {
+        if !self.require_length(1) {
+            return Ok(None);
+        }
         let ret: u8 = self.buffer[self.position];
         self.increment_position(1);
-        return Ok(ret);
+        return Ok(Some(ret));
     }
 
     /// Reading UTF-8 encoded string from buffer
-    pub fn read_string(&mut self) -> Result<EncodedString<'a>, ParseError> {
-        let len = self.read_u16();
-        match len {
+    pub fn read_string(&mut self) -> Result<Option<EncodedString<'a>>, ParseError> {
+        let start = self.position;
+        let len_res = match self.read_u16() {
+            Ok(Some(len)) => len,
+            Ok(None) => return Ok(None),
             Err(err) => return Err(err),
-            _ => {},
+        };
+        if !self.require_length(len_res as usize) {
+            self.position = start;
+            return Ok(None);
         }
-        let len_res = len.unwrap();
         let res_str =
             str::from_utf8(&(self.buffer[self.position..(self.position + len_res as usize)]));
         if res_str.is_err() {
@@ -189,48 +333,63 @@ impl<'a> BuffReader<'a> {
             return Err(ParseError::Utf8Error);
         }
         self.increment_position(len_res as usize);
-        return Ok(EncodedString {
+        return Ok(Some(EncodedString {
             string: res_str.unwrap(),
             len: len_res,
-        });
+        }));
     }
 
-    //TODO: Index out of bounce err !!!!!
     /// Read Binary data from buffer
-    pub fn read_binary(&mut self) -> Result<BinaryData<'a>, ParseError> {
-        let len = self.read_u16();
-        match len {
+    pub fn read_binary(&mut self) -> Result<Option<BinaryData<'a>>, ParseError> {
+        let start = self.position;
+        let len_res = match self.read_u16() {
+            Ok(Some(len)) => len,
+            Ok(None) => return Ok(None),
             Err(err) => return Err(err),
-            _ => log::debug!("[parseBinary] let not parsed"),
+        };
+        if !self.require_length(len_res as usize) {
+            self.position = start;
+            return Ok(None);
         }
-        let len_res = len.unwrap();
         let res_bin = &(self.buffer[self.position..(self.position + len_res as usize)]);
-        return Ok(BinaryData {
+        self.increment_position(len_res as usize);
+        return Ok(Some(BinaryData {
             bin: res_bin,
             len: len_res,
-        });
+        }));
     }
 
     /// Read string pair from buffer
-    pub fn read_string_pair(&mut self) -> Result<StringPair<'a>, ParseError> {
-        let name = self.read_string();
-        match name {
+    pub fn read_string_pair(&mut self) -> Result<Option<StringPair<'a>>, ParseError> {
+        let start = self.position;
+        let name = match self.read_string() {
+            Ok(Some(name)) => name,
+            Ok(None) => {
+                self.position = start;
+                return Ok(None);
+            }
             Err(err) => return Err(err),
-            _ => log::debug!("[String pair] name not parsed"),
-        }
-        let value = self.read_string();
-        match value {
+        };
+        let value = match self.read_string() {
+            Ok(Some(value)) => value,
+            Ok(None) => {
+                self.position = start;
+                return Ok(None);
+            }
             Err(err) => return Err(err),
-            _ => log::debug!("[String pair] value not parsed"),
-        }
-        return Ok(StringPair {
-            name: name.unwrap(),
-            value: value.unwrap(),
-        });
+        };
+        return Ok(Some(StringPair { name, value }));
     }
 
-    /// Read payload message from buffer
-    pub fn read_message(&mut self, total_len: usize) -> &'a [u8] {
-        return &self.buffer[self.position..total_len];
+    /// Read payload message from buffer, up to the absolute offset `total_len`. Returns
+    /// `ParseError::IndexOutOfBounce` instead of panicking if `total_len` doesn't describe a
+    /// valid slice of the remaining buffer (e.g. a malformed remaining length from the broker).
+    pub fn read_message(&mut self, total_len: usize) -> Result<&'a [u8], ParseError> {
+        if total_len < self.position || total_len > self.buffer.len() {
+            return Err(ParseError::IndexOutOfBounce);
+        }
+        let res = &self.buffer[self.position..total_len];
+        self.position = total_len;
+        return Ok(res);
     }
 }
\ No newline at end of file